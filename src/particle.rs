@@ -8,10 +8,12 @@
 
 
 use std::f64;
+use std::f32;
 
 extern crate rand;
 use self::rand::Rng;
 extern crate nalgebra as na;
+extern crate toml;
 
 use ggez::{GameResult, Context};
 use ggez::graphics;
@@ -23,12 +25,71 @@ struct Particle {
     pos: Point2,
     vel: Vector2,
     age: f64,
+    /// This particle's own rolled lifetime, sampled from the system's
+    /// `life` param when it was spawned; see `ParticleSystem::emit`.
+    max_life: f64,
+    /// Current facing angle, in radians.
+    rotation: f64,
+    /// Spin, in radians per second.
+    angular_vel: f64,
+    /// A per-particle offset added to the system's `size` curve, sampled
+    /// from `ParticleSystem::size_variance` at spawn time.
+    size_offset: f64,
 }
 
 /// A trait that defines a way to do some sort of
-/// lerp or easing function on a type.
-trait Interpable {
-    fn interp(&self, t: f64) -> Self;
+/// lerp or easing function between two values of a type.
+pub trait Interpable {
+    /// Interpolate between `self` and `other`, where `t == 0.0` yields
+    /// `self` and `t == 1.0` yields `other`.
+    fn interp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Interpable for f64 {
+    fn interp(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpable for Vector2 {
+    fn interp(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+/// A plain RGBA color, channels in `[0.0, 1.0]`, kept separate from
+/// `ggez::graphics::Color` so it can be lerped directly; convert with
+/// `From`/`Into` when it's time to actually draw.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Color { r: r, g: g, b: b, a: a }
+    }
+}
+
+impl Interpable for Color {
+    fn interp(&self, other: &Self, t: f64) -> Self {
+        let t = t as f32;
+        Color {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
+        }
+    }
+}
+
+impl From<Color> for graphics::Color {
+    fn from(c: Color) -> Self {
+        graphics::Color::new(c.r, c.g, c.b, c.a)
+    }
 }
 
 /// A structure that represents a transition between
@@ -42,39 +103,87 @@ trait Interpable {
 /// Though we could fix that just by having or finding some kind of
 /// scaling factor... hmmmm.  Nah, that should be external to the
 /// transition.
-struct Transition<T: Interpable> {
+pub struct Transition<T: Interpable + Clone> {
     breakpoints: Vec<(f64, T)>,
 }
 
-impl<T: Interpable> Transition<T> {
+impl<T: Interpable + Clone> Transition<T> {
+    pub fn new() -> Self {
+        Transition { breakpoints: Vec::new() }
+    }
+
     /// Add a new breakpoint to the transition
-    /// at time 0 < t < 1
-    fn add(&mut self, t: f64, val: T) {}
+    /// at time 0 <= t <= 1, keeping `breakpoints` sorted by `t`.
+    pub fn add(&mut self, t: f64, val: T) {
+        let idx = match self.breakpoints.binary_search_by(|bp| bp.0.partial_cmp(&t).unwrap()) {
+            Ok(i) | Err(i) => i,
+        };
+        self.breakpoints.insert(idx, (t, val));
+    }
+
+    /// Evaluate the transition at `t`, clamped to `[0, 1]`.  Outside the
+    /// first and last breakpoint the value is held constant; in between,
+    /// the two surrounding breakpoints are found by binary search and
+    /// interpolated between.
+    fn interp_at(&self, t: f64) -> T {
+        assert!(!self.breakpoints.is_empty(), "Transition has no breakpoints set");
+        let t = t.max(0.0).min(1.0);
+        let first = &self.breakpoints[0];
+        let last = &self.breakpoints[self.breakpoints.len() - 1];
+        if t <= first.0 {
+            return first.1.clone();
+        }
+        if t >= last.0 {
+            return last.1.clone();
+        }
+        let idx = match self.breakpoints.binary_search_by(|bp| bp.0.partial_cmp(&t).unwrap()) {
+            Ok(i) => i,
+            Err(i) => i,
+        };
+        let &(t0, ref v0) = &self.breakpoints[idx - 1];
+        let &(t1, ref v1) = &self.breakpoints[idx];
+        let u = (t - t0) / (t1 - t0);
+        v0.interp(v1, u)
+    }
 }
 
-enum StartParam<T> {
+/// A starting value for some per-particle property: either a fixed
+/// constant, a uniform range, or a normal distribution to draw a fresh
+/// value from each time a particle is spawned.
+pub enum StartParam<T> {
     Fixed(T),
     UniformRange(T, T),
+    /// `Normal(mean, stddev)`.
+    Normal(T, T),
 }
 
-use self::rand::distributions::Sample;
+/// Box-Muller transform: turn two uniform samples in `(0, 1]`/`[0, 1)`
+/// into one standard-normal sample.
+fn box_muller(u1: f64, u2: f64) -> f64 {
+    (-2.0 * u1.ln()).sqrt() * (2.0 * f64::consts::PI * u2).cos()
+}
 
-impl<T> Sample<f64> for StartParam<T> {
-    fn sample<R: Rng>(&mut self, rng: &mut R) -> f64 {
-        let rand::Open01(val) = rand::random::<rand::Open01<f64>>();
-        val
-    }
+/// `rng.gen_range(low, high)` panics on a degenerate `low == high` range;
+/// a `UniformRange` with no actual spread is valid input (e.g. from a
+/// config's `foo_rng = 0.0`), so just return the fixed point instead.
+fn gen_range_f64<R: Rng>(rng: &mut R, low: f64, high: f64) -> f64 {
+    if low == high { low } else { rng.gen_range(low, high) }
 }
 
+fn gen_range_f32<R: Rng>(rng: &mut R, low: f32, high: f32) -> f32 {
+    if low == high { low } else { rng.gen_range(low, high) }
+}
 
 impl StartParam<f64> {
-    fn get_value(self) -> f64 {
-        match self {
+    fn get_value(&self) -> f64 {
+        let mut rng = rand::thread_rng();
+        match *self {
             StartParam::Fixed(x) => x,
-            StartParam::UniformRange(ref low, ref high) => {
-                //let mut rng = rand::thread_rng();
-                //rng.gen()
-                rand::random::<StartParam<f64>>()
+            StartParam::UniformRange(low, high) => gen_range_f64(&mut rng, low, high),
+            StartParam::Normal(mean, stddev) => {
+                let u1: f64 = rng.gen_range(f64::EPSILON, 1.0);
+                let u2: f64 = rng.gen_range(0.0, 1.0);
+                mean + stddev * box_muller(u1, u2)
             }
         }
     }
@@ -82,12 +191,65 @@ impl StartParam<f64> {
 
 
 impl StartParam<f32> {
-    fn get_value(self) -> f32 {
-        match self {
+    fn get_value(&self) -> f32 {
+        let mut rng = rand::thread_rng();
+        match *self {
             StartParam::Fixed(x) => x,
-            StartParam::UniformRange(ref low, ref high) => {
-                let mut rng = rand::thread_rng();
-                rng.gen()
+            StartParam::UniformRange(low, high) => gen_range_f32(&mut rng, low, high),
+            StartParam::Normal(mean, stddev) => {
+                let u1 = rng.gen_range(f32::EPSILON, 1.0) as f64;
+                let u2 = rng.gen_range(0.0, 1.0) as f64;
+                mean + stddev * box_muller(u1, u2) as f32
+            }
+        }
+    }
+}
+
+impl StartParam<Vector2> {
+    fn get_value(&self) -> Vector2 {
+        let mut rng = rand::thread_rng();
+        match *self {
+            StartParam::Fixed(v) => v,
+            StartParam::UniformRange(low, high) => {
+                Vector2::new(gen_range_f64(&mut rng, low.x, high.x),
+                             gen_range_f64(&mut rng, low.y, high.y))
+            }
+            StartParam::Normal(mean, stddev) => {
+                let u1: f64 = rng.gen_range(f64::EPSILON, 1.0);
+                let u2: f64 = rng.gen_range(0.0, 1.0);
+                let x = mean.x + stddev.x * box_muller(u1, u2);
+                let u3: f64 = rng.gen_range(f64::EPSILON, 1.0);
+                let u4: f64 = rng.gen_range(0.0, 1.0);
+                let y = mean.y + stddev.y * box_muller(u3, u4);
+                Vector2::new(x, y)
+            }
+        }
+    }
+}
+
+/// The area over which newly-spawned particles are placed, relative to
+/// the emitter's position.
+pub enum SpawnArea {
+    Point,
+    Rect { w: f64, h: f64 },
+    Circle { radius: f64 },
+}
+
+impl SpawnArea {
+    /// Sample a random offset from the emitter's origin within this area.
+    fn sample(&self) -> Vector2 {
+        let mut rng = rand::thread_rng();
+        match *self {
+            SpawnArea::Point => Vector2::new(0.0, 0.0),
+            SpawnArea::Rect { w, h } => {
+                Vector2::new(rng.gen_range(-w / 2.0, w / 2.0), rng.gen_range(-h / 2.0, h / 2.0))
+            }
+            SpawnArea::Circle { radius } => {
+                let u1: f64 = rng.gen_range(0.0, 1.0);
+                let u2: f64 = rng.gen_range(0.0, 1.0);
+                let r = radius * u1.sqrt();
+                let theta = 2.0 * f64::consts::PI * u2;
+                Vector2::new(r * theta.cos(), r * theta.sin())
             }
         }
     }
@@ -147,11 +309,21 @@ impl StartParam<f32> {
 // that could get a bit sticky.  :/
 
 impl Particle {
-    fn new(pos: Point2, vel: Vector2) -> Self {
+    fn new(pos: Point2,
+           vel: Vector2,
+           max_life: f64,
+           rotation: f64,
+           angular_vel: f64,
+           size_offset: f64)
+           -> Self {
         Particle {
             pos: pos,
             vel: vel,
             age: 0.0,
+            max_life: max_life,
+            rotation: rotation,
+            angular_vel: angular_vel,
+            size_offset: size_offset,
         }
     }
 }
@@ -182,57 +354,315 @@ impl ParticleSystemBuilder {
     }
 
     pub fn lifetime(mut self, time: f64) -> Self {
-        self.system.max_life = time;
+        self.system.life = StartParam::Fixed(time);
         self
+    }
 
+    /// Each particle rolls its own lifetime uniformly from `[low, high]`.
+    pub fn lifetime_range(mut self, low: f64, high: f64) -> Self {
+        self.system.life = StartParam::UniformRange(low, high);
+        self
     }
 
     pub fn acceleration(mut self, accel: Vector2) -> Self {
         self.system.acceleration = accel;
         self
     }
+
+    /// Set the emitter's position. Particles spawn here (offset by the
+    /// `spawn_area`) and it's the reference point `radial_accel`,
+    /// `tangential_accel`, and `origin_attraction` pull relative to.
+    pub fn origin(mut self, origin: Point2) -> Self {
+        self.system.origin = origin;
+        self
+    }
+
+    /// Acceleration applied along the vector from `origin` to each
+    /// particle; positive values push particles outward.
+    pub fn radial_accel(mut self, accel: f64) -> Self {
+        self.system.radial_accel = accel;
+        self
+    }
+
+    /// Acceleration applied perpendicular to the radial direction
+    /// (rotated 90 degrees counterclockwise), for swirls and vortices.
+    pub fn tangential_accel(mut self, accel: f64) -> Self {
+        self.system.tangential_accel = accel;
+        self
+    }
+
+    /// Acceleration pulling particles back toward `origin`, proportional
+    /// to their distance from it, for fountains that fall back in on
+    /// themselves.
+    pub fn origin_attraction(mut self, accel: f64) -> Self {
+        self.system.origin_attraction = accel;
+        self
+    }
+
+    /// Fraction of velocity lost per second to friction/drag.
+    pub fn linear_damping(mut self, damping: f64) -> Self {
+        self.system.linear_damping = damping;
+        self
+    }
+
+    /// Set the size curve particles follow over their lifetime.
+    pub fn size(mut self, size: Transition<f64>) -> Self {
+        self.system.size = size;
+        self
+    }
+
+    /// Set the color curve particles follow over their lifetime.
+    pub fn color(mut self, color: Transition<Color>) -> Self {
+        self.system.color = color;
+        self
+    }
+
+    /// Set the distribution each particle draws a one-off offset from,
+    /// added to every point on the `size` curve for that particle's
+    /// whole life. Lets two particles on the same curve still come out
+    /// different sizes.
+    pub fn size_variance(mut self, variance: StartParam<f64>) -> Self {
+        self.system.size_variance = variance;
+        self
+    }
+
+    /// Set the steady-state emission rate, in particles per second.
+    pub fn emission_rate(mut self, rate: f64) -> Self {
+        self.system.emission_rate = rate;
+        self
+    }
+
+    /// Set how long, in seconds, the emitter emits at `emission_rate`
+    /// before it goes quiet.  Bursts are unaffected by this.
+    pub fn emitter_lifetime(mut self, time: f64) -> Self {
+        self.system.emitter_lifetime = time;
+        self
+    }
+
+    /// Set the one-off bursts of particles fired as the emitter ages.
+    pub fn bursts(mut self, bursts: Vec<ParticleBurst>) -> Self {
+        self.system.bursts = bursts;
+        self
+    }
+
+    /// Set the area over which new particles are spawned.
+    pub fn spawn_area(mut self, area: SpawnArea) -> Self {
+        self.system.spawn_area = area;
+        self
+    }
+
+    /// Set the distribution new particles draw their starting velocity from.
+    pub fn start_velocity(mut self, vel: StartParam<Vector2>) -> Self {
+        self.system.start_velocity = vel;
+        self
+    }
+
+    /// Set the sprite drawn for each particle. Falls back to a plain
+    /// colored rectangle when unset.
+    pub fn image(mut self, image: graphics::Image) -> Self {
+        self.system.image = Some(image);
+        self
+    }
+
+    /// Set how overlapping particles blend together when drawn.
+    pub fn blend_mode(mut self, mode: BlendMode) -> Self {
+        self.system.blend_mode = mode;
+        self
+    }
+
+    /// Set the distribution each particle draws its starting rotation
+    /// (in radians) from.
+    pub fn rotation(mut self, rotation: StartParam<f64>) -> Self {
+        self.system.rotation = rotation;
+        self
+    }
+
+    /// Set the distribution each particle draws its spin (angular
+    /// velocity, in radians per second) from.
+    pub fn spin(mut self, spin: StartParam<f64>) -> Self {
+        self.system.spin = spin;
+        self
+    }
+
+    /// When set, each particle's starting rotation is offset by the
+    /// angle of its spawn velocity, so sprites point the way they're
+    /// launched; useful for directional debris and sparks.
+    pub fn rotation_relative_to_spawn_direction(mut self, relative: bool) -> Self {
+        self.system.rotation_relative_to_spawn_direction = relative;
+        self
+    }
+}
+
+/// A one-off burst of `count` particles, fired once the emitter's age
+/// crosses `time` seconds.  Bursts count toward `max_particles` but do
+/// not draw from the steady-state `emission_rate` accumulator.
+pub struct ParticleBurst {
+    time: f64,
+    count: usize,
+    fired: bool,
+}
+
+impl ParticleBurst {
+    pub fn new(time: f64, count: usize) -> Self {
+        ParticleBurst {
+            time: time,
+            count: count,
+            fired: false,
+        }
+    }
 }
 
 
 pub struct ParticleSystem {
     particles: Vec<Particle>,
     max_particles: usize,
-    max_life: f64,
+    /// Distribution each particle draws its own lifetime from at spawn.
+    life: StartParam<f64>,
     acceleration: Vector2,
+    /// The emitter's position in the system's coordinate space; new
+    /// particles spawn here and radial/tangential forces are computed
+    /// relative to it.
+    origin: Point2,
+    radial_accel: f64,
+    tangential_accel: f64,
+    origin_attraction: f64,
+    linear_damping: f64,
+    size: Transition<f64>,
+    /// Per-particle offset to `size`, sampled at spawn time; see `size_variance`.
+    size_variance: StartParam<f64>,
+    color: Transition<Color>,
+    emission_rate: f64,
+    emission_accumulator: f64,
+    emitter_age: f64,
+    emitter_lifetime: f64,
+    bursts: Vec<ParticleBurst>,
+    spawn_area: SpawnArea,
+    start_velocity: StartParam<Vector2>,
+    image: Option<graphics::Image>,
+    blend_mode: BlendMode,
+    rotation: StartParam<f64>,
+    spin: StartParam<f64>,
+    rotation_relative_to_spawn_direction: bool,
+}
+
+/// How overlapping particles combine when drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlendMode {
+    /// Normal painter's-algorithm alpha blending.
+    Alpha,
+    /// Additive blending, so overlapping particles accumulate brightness;
+    /// good for fire, sparks, and other glow effects.
+    Additive,
+}
+
+impl From<BlendMode> for graphics::BlendMode {
+    fn from(b: BlendMode) -> Self {
+        match b {
+            BlendMode::Alpha => graphics::BlendMode::Alpha,
+            BlendMode::Additive => graphics::BlendMode::Add,
+        }
+    }
 }
 
 impl ParticleSystem {
     pub fn new() -> Self {
-        ParticleSystem { 
-            particles: Vec::new(), 
+        let mut size = Transition::new();
+        size.add(0.0, 5.0);
+        let mut color = Transition::new();
+        color.add(0.0, Color::new(1.0, 1.0, 1.0, 1.0));
+        ParticleSystem {
+            particles: Vec::new(),
             max_particles: 0 ,
-            max_life: f64::INFINITY,
+            life: StartParam::Fixed(f64::INFINITY),
             acceleration: Vector2::new(0.0, 0.0),
+            origin: Point2::new(0.0, 0.0),
+            radial_accel: 0.0,
+            tangential_accel: 0.0,
+            origin_attraction: 0.0,
+            linear_damping: 0.0,
+            size: size,
+            size_variance: StartParam::Fixed(0.0),
+            color: color,
+            emission_rate: 0.0,
+            emission_accumulator: 0.0,
+            emitter_age: 0.0,
+            emitter_lifetime: f64::INFINITY,
+            bursts: Vec::new(),
+            spawn_area: SpawnArea::Point,
+            start_velocity: StartParam::Fixed(Vector2::new(10.0, 10.0)),
+            image: None,
+            blend_mode: BlendMode::Alpha,
+            rotation: StartParam::Fixed(0.0),
+            spin: StartParam::Fixed(0.0),
+            rotation_relative_to_spawn_direction: false,
         }
     }
 
     pub fn emit(&mut self) {
-        let pos = Point2::new(0.0, 0.0);
-        let vec = Vector2::new(10.0, 10.0);
-        let newparticle = Particle::new(pos, vec);
+        let offset = self.spawn_area.sample();
+        let pos = self.origin + offset;
+        let vel = self.start_velocity.get_value();
+        let max_life = self.life.get_value();
+        let mut rotation = self.rotation.get_value();
+        if self.rotation_relative_to_spawn_direction {
+            rotation += vel.y.atan2(vel.x);
+        }
+        let angular_vel = self.spin.get_value();
+        let size_offset = self.size_variance.get_value();
+        let newparticle = Particle::new(pos, vel, max_life, rotation, angular_vel, size_offset);
         self.add_particle(newparticle);
     }
 
     pub fn update(&mut self, dt: f64) {
+        let origin = self.origin;
         for mut p in self.particles.iter_mut() {
+            let to_particle = p.pos - origin;
+            let dist = to_particle.norm();
+            if dist > 0.0 {
+                let radial_dir = to_particle / dist;
+                let tangential_dir = Vector2::new(-radial_dir.y, radial_dir.x);
+                p.vel += radial_dir * self.radial_accel * dt;
+                p.vel += tangential_dir * self.tangential_accel * dt;
+            }
+            p.vel += -to_particle * self.origin_attraction * dt;
             p.vel += self.acceleration * dt;
+            p.vel *= 1.0 - self.linear_damping * dt;
             p.pos += p.vel * dt;
+            p.rotation += p.angular_vel * dt;
             p.age += dt;
         }
 
-        // Gotta make borrowck happy by not referring
-        // to self in the same closure twice.
-        let max_life = self.max_life;
-        self.particles.retain(|p| p.age < max_life);
+        self.particles.retain(|p| p.age < p.max_life);
+
+        self.emitter_age += dt;
+        if self.emitter_age <= self.emitter_lifetime {
+            self.emission_accumulator += self.emission_rate * dt;
+            let to_emit = self.emission_accumulator.floor();
+            self.emission_accumulator -= to_emit;
+            for _ in 0..(to_emit as usize) {
+                self.emit();
+            }
+        }
+
+        let emitter_age = self.emitter_age;
+        let mut to_fire = Vec::new();
+        for burst in self.bursts.iter_mut() {
+            if !burst.fired && emitter_age >= burst.time {
+                burst.fired = true;
+                to_fire.push(burst.count);
+            }
+        }
+        for count in to_fire {
+            for _ in 0..count {
+                self.emit();
+            }
+        }
     }
 
-    fn calc_particle_size(&self, idx: usize) -> u32 {
-        5
+    /// Where a particle is in its life, as `0.0` (just spawned) to
+    /// `1.0` (about to be retired), for evaluating `size`/`color`.
+    fn particle_t(&self, p: &Particle) -> f64 {
+        p.age / p.max_life
     }
 
     /// Adds a new particle to the system, if it would
@@ -244,6 +674,192 @@ impl ParticleSystem {
     }
 }
 
+/// An error parsing a particle system definition.
+#[derive(Debug)]
+pub enum ConfigError {
+    Parse(String),
+    Type(&'static str),
+}
+
+impl ParticleSystem {
+    /// Parse a particle system definition from a TOML document, in the
+    /// spirit of the Tremulous `.particle` / Galactica effect files:
+    /// `sprite`, `lifetime` (+ `lifetime_rng`), `size` (+ `size_rng`),
+    /// `fade`, `color` ramp, `emission_rate`, `bursts`, `acceleration`,
+    /// and a spawn area.  Most base keys `foo` paired with a `foo_rng`
+    /// key become a `StartParam::UniformRange(foo - foo_rng, foo +
+    /// foo_rng)` instead of a fixed value, so non-programmers can tune
+    /// variance without touching builder code.  `size_rng` is the
+    /// exception: `size` is a shared `Transition` curve, not a
+    /// per-particle value, so `size_rng` instead rolls a one-off
+    /// per-particle offset added to that curve (see `size_variance`).
+    /// `sprite` is ignored here since
+    /// resolving it to an `Image` needs a `Context`; use `from_file`.
+    pub fn from_config_str(s: &str) -> Result<ParticleSystemBuilder, ConfigError> {
+        let value = s.parse::<toml::Value>().map_err(|e| ConfigError::Parse(e.to_string()))?;
+        let table = value.as_table().ok_or_else(|| ConfigError::Type("document root must be a table"))?;
+        let mut builder = ParticleSystemBuilder::new();
+
+        if let Some(n) = table.get("count").and_then(toml::Value::as_integer) {
+            builder = builder.count(n as usize);
+        }
+        match param_f64(table, "lifetime") {
+            Some(StartParam::Fixed(v)) => builder = builder.lifetime(v),
+            Some(StartParam::UniformRange(lo, hi)) => builder = builder.lifetime_range(lo, hi),
+            _ => {}
+        }
+        if let Some(accel) = vector_field(table, "acceleration") {
+            builder = builder.acceleration(accel);
+        }
+        if let Some(rate) = table.get("emission_rate").and_then(as_f64) {
+            builder = builder.emission_rate(rate);
+        }
+        if let Some(time) = table.get("emitter_lifetime").and_then(as_f64) {
+            builder = builder.emitter_lifetime(time);
+        }
+        if let Some(vel) = velocity_field(table) {
+            builder = builder.start_velocity(vel);
+        }
+        if let Some(area) = spawn_area_field(table) {
+            builder = builder.spawn_area(area);
+        }
+        if let Some(bursts) = table.get("bursts").and_then(toml::Value::as_array) {
+            let parsed: Vec<ParticleBurst> = bursts.iter()
+                .filter_map(toml::Value::as_table)
+                .filter_map(|b| {
+                    let time = b.get("time").and_then(as_f64)?;
+                    let count = b.get("count").and_then(toml::Value::as_integer)?;
+                    Some(ParticleBurst::new(time, count as usize))
+                })
+                .collect();
+            builder = builder.bursts(parsed);
+        }
+
+        let size = size_transition(table);
+        builder = builder.size(size);
+        if let Some(rng) = table.get("size_rng").and_then(as_f64) {
+            builder = builder.size_variance(StartParam::UniformRange(-rng, rng));
+        }
+        if let Some(ramp) = table.get("color").and_then(toml::Value::as_array) {
+            let mut transition = Transition::new();
+            for entry in ramp {
+                if let Some(row) = entry.as_array() {
+                    if row.len() == 5 {
+                        let vals = (as_f64(&row[0]), as_f64(&row[1]), as_f64(&row[2]),
+                                    as_f64(&row[3]), as_f64(&row[4]));
+                        if let (Some(t), Some(r), Some(g), Some(b), Some(a)) = vals {
+                            transition.add(t, Color::new(r as f32, g as f32, b as f32, a as f32));
+                        }
+                    }
+                }
+            }
+            builder = builder.color(transition);
+        }
+
+        Ok(builder)
+    }
+
+    /// Like `from_config_str`, but also resolves `sprite = "..."` to an
+    /// `Image` loaded through `ctx`, matching the image path conventions
+    /// `graphics::Image::new` expects.
+    pub fn from_file(ctx: &mut Context, s: &str) -> Result<ParticleSystem, ConfigError> {
+        let value = s.parse::<toml::Value>().map_err(|e| ConfigError::Parse(e.to_string()))?;
+        let table = value.as_table().ok_or_else(|| ConfigError::Type("document root must be a table"))?;
+        let mut builder = Self::from_config_str(s)?;
+        if let Some(path) = table.get("sprite").and_then(toml::Value::as_str) {
+            let image = graphics::Image::new(ctx, path).map_err(|e| ConfigError::Parse(e.to_string()))?;
+            builder = builder.image(image);
+        }
+        Ok(builder.build())
+    }
+}
+
+/// Read a TOML value as an `f64`, accepting either a float or an integer
+/// literal (`size = 5` should work just as well as `size = 5.0` for a
+/// config format aimed at non-programmers).
+fn as_f64(v: &toml::Value) -> Option<f64> {
+    v.as_float().or_else(|| v.as_integer().map(|i| i as f64))
+}
+
+/// Read `table[key]`/`table[key_rng]` as a `StartParam<f64>`: a fixed
+/// value, or a uniform range around it when the `_rng` variant is present.
+fn param_f64(table: &toml::value::Table, key: &str) -> Option<StartParam<f64>> {
+    let base = as_f64(table.get(key)?)?;
+    let rng_key = format!("{}_rng", key);
+    match table.get(&rng_key).and_then(as_f64) {
+        Some(rng) => Some(StartParam::UniformRange(base - rng, base + rng)),
+        None => Some(StartParam::Fixed(base)),
+    }
+}
+
+fn vector_field(table: &toml::value::Table, key: &str) -> Option<Vector2> {
+    let entry = table.get(key)?.as_array()?;
+    if entry.len() == 2 {
+        if let (Some(x), Some(y)) = (as_f64(&entry[0]), as_f64(&entry[1])) {
+            return Some(Vector2::new(x, y));
+        }
+    }
+    None
+}
+
+/// Build the particle start velocity from `velocity_x`/`velocity_y`,
+/// each independently either a fixed value or (with a `_rng` variant) a
+/// uniform range, defaulting to the existing hardcoded `10.0` per axis
+/// when unset. Setting variance on only one axis is valid: the other axis
+/// becomes a zero-width range, which `gen_range_f64` returns as-is.
+fn velocity_field(table: &toml::value::Table) -> Option<StartParam<Vector2>> {
+    let vx = param_f64(table, "velocity_x");
+    let vy = param_f64(table, "velocity_y");
+    if vx.is_none() && vy.is_none() {
+        return None;
+    }
+    let (xlo, xhi) = range_of(vx.unwrap_or(StartParam::Fixed(10.0)));
+    let (ylo, yhi) = range_of(vy.unwrap_or(StartParam::Fixed(10.0)));
+    if xlo == xhi && ylo == yhi {
+        Some(StartParam::Fixed(Vector2::new(xlo, ylo)))
+    } else {
+        Some(StartParam::UniformRange(Vector2::new(xlo, ylo), Vector2::new(xhi, yhi)))
+    }
+}
+
+fn range_of(p: StartParam<f64>) -> (f64, f64) {
+    match p {
+        StartParam::Fixed(v) => (v, v),
+        StartParam::UniformRange(lo, hi) => (lo, hi),
+        StartParam::Normal(mean, stddev) => (mean - stddev, mean + stddev),
+    }
+}
+
+fn spawn_area_field(table: &toml::value::Table) -> Option<SpawnArea> {
+    let kind = table.get("spawn_area").and_then(toml::Value::as_table)?;
+    match kind.get("type").and_then(toml::Value::as_str) {
+        Some("rect") => {
+            let w = kind.get("w").and_then(as_f64).unwrap_or(0.0);
+            let h = kind.get("h").and_then(as_f64).unwrap_or(0.0);
+            Some(SpawnArea::Rect { w: w, h: h })
+        }
+        Some("circle") => {
+            let radius = kind.get("radius").and_then(as_f64).unwrap_or(0.0);
+            Some(SpawnArea::Circle { radius: radius })
+        }
+        _ => Some(SpawnArea::Point),
+    }
+}
+
+/// Build a two-point size `Transition` from `size` (the starting size)
+/// and `fade` (a multiplier applied by the end of life). `size_rng` is
+/// read separately in `from_config_str` into a `size_variance` param,
+/// since it rolls a per-particle offset rather than shaping this curve.
+fn size_transition(table: &toml::value::Table) -> Transition<f64> {
+    let mut transition = Transition::new();
+    let start = table.get("size").and_then(as_f64).unwrap_or(5.0);
+    transition.add(0.0, start);
+    if let Some(fade) = table.get("fade").and_then(as_f64) {
+        transition.add(1.0, start * fade);
+    }
+    transition
+}
+
 impl graphics::Drawable for ParticleSystem {
     fn draw_ex(&self,
                context: &mut Context,
@@ -261,14 +877,103 @@ impl graphics::Drawable for ParticleSystem {
         // expensive(ish).
         // Maybe we can make it an x and y scale?  Hmm.
         let dst_rect = dst.unwrap_or(graphics::Rect::new(0, 0, 0, 0));
-        for (i,p) in self.particles.iter().enumerate() {
-            let p_size = self.calc_particle_size(i);
+        let prior_color = graphics::get_color(context);
+        let prior_blend_mode = graphics::get_blend_mode(context);
+        graphics::set_blend_mode(context, self.blend_mode.into())?;
+        for p in self.particles.iter() {
+            let t = self.particle_t(p);
+            let p_size = (self.size.interp_at(t) + p.size_offset).max(0.0) as u32;
+            let p_color = self.color.interp_at(t);
+            graphics::set_color(context, p_color.into())?;
             let rect = graphics::Rect::new(dst_rect.x() + p.pos.x as i32,
                                            dst_rect.y() + p.pos.y as i32,
                                            p_size,
                                            p_size);
-            graphics::rectangle(context, graphics::DrawMode::Fill, rect)?;
+            match self.image {
+                // Tint (multiply by color, as in Godot's ParticlesMaterial)
+                // is already applied via set_color above; the image itself
+                // just needs to be scaled to the particle's current size.
+                Some(ref image) => {
+                    image.draw_ex(context, src, Some(rect), angle + p.rotation, center, flip_horizontal, flip_vertical)?;
+                }
+                None => {
+                    graphics::rectangle(context, graphics::DrawMode::Fill, rect)?;
+                }
+            }
         }
+        graphics::set_blend_mode(context, prior_blend_mode)?;
+        graphics::set_color(context, prior_color)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transition_interp_at_endpoints_and_midpoint() {
+        let mut t = Transition::new();
+        t.add(0.0, 0.0);
+        t.add(1.0, 10.0);
+        assert_eq!(t.interp_at(0.0), 0.0);
+        assert_eq!(t.interp_at(1.0), 10.0);
+        assert_eq!(t.interp_at(0.5), 5.0);
+    }
+
+    #[test]
+    fn transition_interp_at_clamps_and_holds_outside_breakpoints() {
+        let mut t = Transition::new();
+        t.add(0.25, 1.0);
+        t.add(0.75, 2.0);
+        // Before the first and after the last breakpoint, the value is
+        // held constant rather than extrapolated.
+        assert_eq!(t.interp_at(0.0), 1.0);
+        assert_eq!(t.interp_at(1.0), 2.0);
+        assert_eq!(t.interp_at(-1.0), 1.0);
+        assert_eq!(t.interp_at(2.0), 2.0);
+        assert_eq!(t.interp_at(0.5), 1.5);
+    }
+
+    #[test]
+    fn emission_accumulator_only_emits_whole_particles() {
+        let mut sys = ParticleSystemBuilder::new()
+            .count(10)
+            .emission_rate(0.5)
+            .build();
+        // 0.5 particles/sec * 1s = 0.5 accumulated; not enough to emit yet.
+        sys.update(1.0);
+        assert_eq!(sys.particles.len(), 0);
+        // Another 0.5 crosses the 1.0 threshold, emitting exactly one.
+        sys.update(1.0);
+        assert_eq!(sys.particles.len(), 1);
+    }
+
+    #[test]
+    fn emission_accumulator_carries_fractional_remainder() {
+        let mut sys = ParticleSystemBuilder::new()
+            .count(10)
+            .emission_rate(1.5)
+            .build();
+        sys.update(1.0);
+        assert_eq!(sys.particles.len(), 1);
+        // Leftover 0.5 plus this tick's 1.5 crosses two whole particles.
+        sys.update(1.0);
+        assert_eq!(sys.particles.len(), 3);
+    }
+
+    #[test]
+    fn burst_fires_once_when_emitter_age_crosses_its_time() {
+        let mut sys = ParticleSystemBuilder::new()
+            .count(10)
+            .bursts(vec![ParticleBurst::new(1.0, 3)])
+            .build();
+        sys.update(0.5);
+        assert_eq!(sys.particles.len(), 0);
+        sys.update(0.5);
+        assert_eq!(sys.particles.len(), 3);
+        // The burst has already fired and must not fire again.
+        sys.update(1.0);
+        assert_eq!(sys.particles.len(), 3);
+    }
+}